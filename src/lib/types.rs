@@ -1,17 +1,36 @@
-use std::{env, fs::remove_dir_all, path::PathBuf};
+use std::{env, fs::remove_dir_all, path::PathBuf, sync::Arc};
 
 use libflatpak::{
     gio::prelude::FileExt,
-    prelude::{InstallationExt, RemoteExt},
+    prelude::{InstallationExt, RemoteExt, TransactionExt, TransactionOperationExt},
     BundleRef, RefKind,
 };
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub enum FlatpakExtError {
+    /// Catch-all for libflatpak failures that don't map to a more specific variant below.
     Glib(libflatpak::glib::Error),
     IO(std::io::Error),
     Reqwest(reqwest::Error),
+    /// A bundle depends on a runtime that isn't installed and doesn't advertise a repo to fetch
+    /// it from.
+    NoRuntimeRepo(String),
+    /// `FLATPAK_ERROR_ALREADY_INSTALLED`: the ref is already installed. Callers can usually
+    /// treat this as a no-op success rather than a failure.
+    AlreadyInstalled(libflatpak::glib::Error),
+    /// `FLATPAK_ERROR_NOT_INSTALLED`: the ref isn't installed.
+    NotInstalled(libflatpak::glib::Error),
+    /// `FLATPAK_ERROR_RUNTIME_NOT_FOUND`: the runtime a ref depends on couldn't be found in any
+    /// configured remote.
+    RuntimeNotFound(libflatpak::glib::Error),
+    /// `FLATPAK_ERROR_REMOTE_NOT_FOUND`: the named remote isn't configured.
+    RemoteNotFound(libflatpak::glib::Error),
+    /// `FLATPAK_ERROR_NEED_NEW_FLATPAK`: the host's flatpak is too old for this operation.
+    NeedNewFlatpak(libflatpak::glib::Error),
+    /// `FLATPAK_ERROR_DOWNGRADE`: the operation would downgrade an installed ref.
+    Downgrade(libflatpak::glib::Error),
 }
 
 impl From<std::io::Error> for FlatpakExtError {
@@ -22,7 +41,17 @@ impl From<std::io::Error> for FlatpakExtError {
 
 impl From<libflatpak::glib::Error> for FlatpakExtError {
     fn from(value: libflatpak::glib::Error) -> Self {
-        Self::Glib(value)
+        use libflatpak::Error as FlatpakError;
+
+        match value.kind::<FlatpakError>() {
+            Some(FlatpakError::AlreadyInstalled) => Self::AlreadyInstalled(value),
+            Some(FlatpakError::NotInstalled) => Self::NotInstalled(value),
+            Some(FlatpakError::RuntimeNotFound) => Self::RuntimeNotFound(value),
+            Some(FlatpakError::RemoteNotFound) => Self::RemoteNotFound(value),
+            Some(FlatpakError::NeedNewFlatpak) => Self::NeedNewFlatpak(value),
+            Some(FlatpakError::Downgrade) => Self::Downgrade(value),
+            _ => Self::Glib(value),
+        }
     }
 }
 
@@ -32,6 +61,52 @@ impl From<reqwest::Error> for FlatpakExtError {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Coarse-grained stage of a pull/install operation, reported alongside each [`ProgressEvent`].
+pub enum ProgressStatus {
+    /// Ref data is being downloaded from the remote.
+    Pulling,
+    /// Downloaded data is being deployed into the installation.
+    Deploying,
+    /// The operation has finished.
+    Done,
+}
+
+#[derive(Clone, Debug)]
+/// A single update describing how a pull/install operation is progressing.
+pub struct ProgressEvent {
+    /// The ref the current operation acts on (e.g. `app/org.mozilla.firefox/x86_64/stable`), as
+    /// reported by libflatpak; empty if libflatpak reports none.
+    pub operation: String,
+    pub bytes_transferred: u64,
+    pub total_bytes: u64,
+    /// 0-100.
+    pub percent: u8,
+    pub status: ProgressStatus,
+}
+
+/// Where to send [`ProgressEvent`]s for a pull/install operation.
+pub enum ProgressSink {
+    /// Invoke a callback for every event.
+    Callback(Box<dyn Fn(ProgressEvent) + Send>),
+    /// Send every event down an `mpsc` channel, e.g. for a CLI progress bar or GUI frontend
+    /// running on another thread.
+    Channel(std::sync::mpsc::Sender<ProgressEvent>),
+}
+
+impl ProgressSink {
+    fn emit(&self, event: ProgressEvent) {
+        match self {
+            ProgressSink::Callback(callback) => callback(event),
+            ProgressSink::Channel(sender) => {
+                // The receiver may already be gone (e.g. the progress bar exited); that's not
+                // fatal to the install itself.
+                let _ = sender.send(event);
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Flatpak {
     Bundle(PathBuf),
@@ -40,25 +115,85 @@ pub enum Flatpak {
 
 #[derive(Clone, Debug)]
 pub enum FlatpakOut {
-    Bundle(libflatpak::BundleRef),
+    Bundle(PathBuf, libflatpak::BundleRef),
     Download(libflatpak::RemoteRef),
 }
 
+impl FlatpakOut {
+    /// Actually installs (and, for a bundle, verifies) this resolved ref into `installation`.
+    /// [`Flatpak::convert_to_flatpak_out`] only resolves refs; deploying them is a separate,
+    /// explicit step so callers can inspect what's about to be installed first.
+    pub fn install(
+        &self,
+        installation: &libflatpak::Installation,
+        gpg_key: Option<&GpgKey>,
+        progress: Option<Arc<ProgressSink>>,
+    ) -> Result<(), FlatpakExtError> {
+        let gpg_data = gpg_key
+            .map(GpgKey::load)
+            .transpose()?
+            .map(libflatpak::glib::Bytes::from_owned);
+        match self {
+            FlatpakOut::Bundle(path, _bundle) => {
+                let bundle_path = libflatpak::gio::File::for_path(path);
+                run_transaction_with_progress(installation, progress, |transaction| {
+                    transaction.add_install_bundle(&bundle_path, gpg_data.as_ref())
+                })
+            }
+            FlatpakOut::Download(remote_ref) => {
+                use libflatpak::prelude::RefExt;
+
+                run_transaction_with_progress(installation, progress, |transaction| {
+                    transaction.add_install(
+                        &remote_ref.remote_name().unwrap(),
+                        &remote_ref.format_ref().unwrap(),
+                        &[],
+                    )
+                })
+            }
+        }
+    }
+}
+
 impl Flatpak {
+    /// Converts this [`Flatpak`] into a concrete [`FlatpakOut`] ref, ready to be deployed.
+    ///
+    /// When `self` is a [`Flatpak::Bundle`] and `resolve_deps` is `true`, the bundle's embedded
+    /// runtime metadata is used to install the runtime (and any related refs, such as locale
+    /// extensions) from the repo advertised by the bundle before the app itself is deployed.
+    /// Pass `resolve_deps = false` to skip this (e.g. for offline installs where the runtime is
+    /// already known to be present).
+    ///
+    /// `arch` selects which architecture's ref to fetch (e.g. `"aarch64"`), defaulting to the
+    /// host architecture ([`libflatpak::default_arch`]) when `None`.
     pub fn convert_to_flatpak_out(
         &self,
         installation: &libflatpak::Installation,
         remote: &libflatpak::Remote,
         branch: &String,
         is_runtime: bool,
+        resolve_deps: bool,
+        gpg_key: Option<&GpgKey>,
+        progress: Option<Arc<ProgressSink>>,
+        arch: Option<&str>,
     ) -> Result<FlatpakOut, FlatpakExtError> {
         match self {
             Flatpak::Bundle(path) => {
-                let bundle_path = libflatpak::gio::File::for_path(&path);
+                let bundle_path = libflatpak::gio::File::for_path(path);
                 let bundle = BundleRef::new(&bundle_path)?;
-                Ok(FlatpakOut::Bundle(bundle))
+                if resolve_deps {
+                    install_bundle_runtime(installation, &bundle, progress.clone())?;
+                }
+                // `BundleRef::new` only parses the bundle's local metadata; it doesn't install or
+                // verify anything. That happens in `FlatpakOut::install`, mirroring the
+                // `Download` arm, which also only resolves a ref here and leaves deploying it to
+                // the caller.
+                Ok(FlatpakOut::Bundle(path.clone(), bundle))
             }
             Flatpak::Download(app_id) => {
+                let arch = arch
+                    .map(str::to_string)
+                    .or_else(|| libflatpak::default_arch().map(Into::into));
                 Ok(FlatpakOut::Download(installation.fetch_remote_ref_sync(
                     &remote.name().unwrap(),
                     if is_runtime {
@@ -67,7 +202,7 @@ impl Flatpak {
                         RefKind::App
                     },
                     &app_id,
-                    libflatpak::default_arch().as_deref(),
+                    arch.as_deref(),
                     Some(&branch),
                     libflatpak::gio::Cancellable::current().as_ref(),
                 )?))
@@ -76,6 +211,196 @@ impl Flatpak {
     }
 }
 
+/// Derives a flatpak remote name safe to pass to `Installation::add_remote` from a runtime id.
+/// Remote names can't contain `:` or `/` (unlike the `.flatpakrepo` uri they're loaded from), so
+/// this can't just reuse [`Remote::new`]'s default of naming the remote after its uri. The name
+/// is deterministic (no random suffix) so that `add_remote(..., if_needed = true, ...)` recognizes
+/// a remote already added for this runtime and reuses it instead of accumulating a new one on
+/// every bundle install.
+fn runtime_repo_remote_name(runtime_name: &str) -> String {
+    format!("{}-runtime-repo", runtime_name.replace('.', "-"))
+}
+
+/// Runs a one-shot [`libflatpak::Transaction`] built by `add_ops`, forwarding real progress to
+/// `sink` by hooking the transaction's `new-operation`/`operation-done` signals. Each operation's
+/// `download_size` is the genuine total to report, rather than a number guessed from a
+/// percentage, and an operation only counts as [`ProgressStatus::Done`] once libflatpak itself
+/// says it finished.
+fn run_transaction_with_progress(
+    installation: &libflatpak::Installation,
+    sink: Option<Arc<ProgressSink>>,
+    add_ops: impl FnOnce(&libflatpak::Transaction) -> Result<(), libflatpak::glib::Error>,
+) -> Result<(), FlatpakExtError> {
+    let transaction = libflatpak::Transaction::for_installation(
+        installation,
+        libflatpak::gio::Cancellable::current().as_ref(),
+    )?;
+    add_ops(&transaction)?;
+
+    // An already-installed ref (e.g. a locale extension pulled in by an earlier install) isn't a
+    // real failure; tolerate it so one already-present dependency doesn't abort installing the
+    // rest of the batch.
+    transaction.connect_operation_error(|_transaction, _operation, error, _details| {
+        matches!(
+            error.kind::<libflatpak::Error>(),
+            Some(libflatpak::Error::AlreadyInstalled)
+        )
+    });
+
+    if let Some(sink) = sink {
+        let progress_sink = Arc::clone(&sink);
+        transaction.connect_new_operation(move |_transaction, operation, op_progress| {
+            let operation_ref = operation
+                .get_ref()
+                .map(|r| r.to_string())
+                .unwrap_or_default();
+            let total_bytes = operation.download_size();
+            let sink = Arc::clone(&progress_sink);
+            op_progress.connect_changed(move |op_progress| {
+                let percent = op_progress.progress().min(100) as u8;
+                sink.emit(ProgressEvent {
+                    operation: operation_ref.clone(),
+                    bytes_transferred: op_progress.bytes_transferred(),
+                    total_bytes,
+                    percent,
+                    // The transaction only reports progress while pulling; once it reaches
+                    // 100% here, libflatpak has moved on to committing/deploying (which isn't
+                    // itself progress-tracked) until `operation-done` fires below.
+                    status: if percent >= 100 {
+                        ProgressStatus::Deploying
+                    } else {
+                        ProgressStatus::Pulling
+                    },
+                });
+            });
+        });
+
+        let done_sink = Arc::clone(&sink);
+        transaction.connect_operation_done(move |_transaction, operation, _commit, _result| {
+            done_sink.emit(ProgressEvent {
+                operation: operation
+                    .get_ref()
+                    .map(|r| r.to_string())
+                    .unwrap_or_default(),
+                bytes_transferred: operation.download_size(),
+                total_bytes: operation.download_size(),
+                percent: 100,
+                status: ProgressStatus::Done,
+            });
+        });
+    }
+
+    transaction.run(libflatpak::gio::Cancellable::current().as_ref())?;
+    Ok(())
+}
+
+/// Makes sure the runtime a bundle depends on (plus any related refs, such as locale
+/// extensions) is installed in `installation`, fetching it from the bundle's embedded
+/// runtime repo if necessary.
+fn install_bundle_runtime(
+    installation: &libflatpak::Installation,
+    bundle: &libflatpak::BundleRef,
+    progress: Option<Arc<ProgressSink>>,
+) -> Result<(), FlatpakExtError> {
+    use libflatpak::prelude::RefExt;
+
+    let Some(runtime) = bundle.runtime() else {
+        // App doesn't declare a runtime dependency (or is a runtime itself); nothing to do.
+        return Ok(());
+    };
+    let runtime_name = runtime.name().unwrap().to_string();
+    let runtime_branch = runtime.branch().unwrap().to_string();
+    let runtime_arch = runtime.arch().map(|a| a.to_string());
+
+    if installation
+        .installed_ref(
+            RefKind::Runtime,
+            &runtime_name,
+            runtime_arch.as_deref(),
+            Some(&runtime_branch),
+            libflatpak::gio::Cancellable::current().as_ref(),
+        )
+        .is_ok()
+    {
+        // Runtime is already installed; nothing left to resolve.
+        return Ok(());
+    }
+
+    let Some(repo_url) = bundle.runtime_repo_url() else {
+        return Err(FlatpakExtError::NoRuntimeRepo(runtime_name));
+    };
+
+    // Note: the bundle's own GPG key (if any) verifies the bundle itself, not this remote. The
+    // runtime repo carries its own key (if it requires verification) in its `.flatpakrepo`, which
+    // `Remote::try_from` already picks up from the fetched bytes; forcing the bundle's key onto
+    // it would just make a correctly-signed runtime repo fail to verify.
+    let runtime_remote =
+        Remote::new(repo_url.to_string()).with_name(runtime_repo_remote_name(&runtime_name));
+    let runtime_remote: libflatpak::Remote = runtime_remote.try_into()?;
+    let runtime_remote_name = runtime_remote.name().unwrap().to_string();
+    installation.add_remote(
+        &runtime_remote,
+        true,
+        libflatpak::gio::Cancellable::current().as_ref(),
+    )?;
+
+    let runtime_ref = installation.fetch_remote_ref_sync(
+        &runtime_remote_name,
+        RefKind::Runtime,
+        &runtime_name,
+        runtime_arch.as_deref(),
+        Some(&runtime_branch),
+        libflatpak::gio::Cancellable::current().as_ref(),
+    )?;
+
+    let related_refs = installation.list_remote_related_refs_sync(
+        &runtime_remote_name,
+        &runtime_ref.format_ref().unwrap(),
+        libflatpak::gio::Cancellable::current().as_ref(),
+    )?;
+
+    // Install the runtime and all its related refs (e.g. locale extensions) as one transaction,
+    // so progress and failure reporting cover the whole dependency set together.
+    run_transaction_with_progress(installation, progress, |transaction| {
+        transaction.add_install(
+            &runtime_remote_name,
+            &runtime_ref.format_ref().unwrap(),
+            &[],
+        )?;
+        for related in &related_refs {
+            transaction.add_install(&runtime_remote_name, &related.format_ref().unwrap(), &[])?;
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
+/// Where to load GPG key material from when verifying a remote or bundle's signature.
+pub enum GpgKey {
+    /// Read raw (binary or ASCII-armored) GPG key data from a file on disk.
+    Path(PathBuf),
+    /// Already-loaded raw GPG key data.
+    Bytes(Vec<u8>),
+    /// Read raw GPG key data from stdin (`-`).
+    Stdin,
+}
+
+impl GpgKey {
+    fn load(&self) -> Result<Vec<u8>, FlatpakExtError> {
+        match self {
+            GpgKey::Path(path) => Ok(std::fs::read(path)?),
+            GpgKey::Bytes(bytes) => Ok(bytes.clone()),
+            GpgKey::Stdin => {
+                let mut buf = Vec::new();
+                std::io::Read::read_to_end(&mut std::io::stdin(), &mut buf)?;
+                Ok(buf)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 /// A remote to download from
 pub struct Remote {
@@ -83,6 +408,8 @@ pub struct Remote {
     uri: String,
     name: String,
     pub default_branch: String,
+    /// GPG key to verify this remote's (and refs pulled from it) signatures against.
+    gpg_key: Option<GpgKey>,
 }
 
 impl Default for Remote {
@@ -91,6 +418,7 @@ impl Default for Remote {
             uri: "https://dl.flathub.org/repo/flathub.flatpakrepo".into(),
             name: "flathub".into(),
             default_branch: "stable".into(),
+            gpg_key: None,
         }
     }
 }
@@ -101,8 +429,52 @@ impl Remote {
             uri: uri.clone(),
             name: uri.clone(),
             default_branch: "master".into(),
+            gpg_key: None,
         }
     }
+
+    /// Attaches GPG key material so pulls from this remote are verified against it.
+    pub fn with_gpg_key(mut self, gpg_key: GpgKey) -> Self {
+        self.gpg_key = Some(gpg_key);
+        self
+    }
+
+    /// Overrides the flatpak remote name that will be registered for this repo. Use this
+    /// instead of the default (which reuses the uri) when the uri itself isn't a valid
+    /// ostree/flatpak remote name, e.g. it contains `:` or `/`.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Fetches and summarizes what this remote actually offers (title, refs, etc.), so callers
+    /// can inspect a `.flatpakrepo` before committing to installing anything from it. Registers
+    /// `self` against `installation` just long enough to query it, then removes it again.
+    pub fn info(
+        &self,
+        installation: &libflatpak::Installation,
+    ) -> Result<RemoteInfo, FlatpakExtError> {
+        let lf_remote: libflatpak::Remote = self.clone().try_into()?;
+        let already_configured = installation
+            .remote_by_name(
+                &lf_remote.name().unwrap(),
+                libflatpak::gio::Cancellable::current().as_ref(),
+            )
+            .is_ok();
+        installation.add_remote(
+            &lf_remote,
+            true,
+            libflatpak::gio::Cancellable::current().as_ref(),
+        )?;
+        let info = lf_remote.info(installation);
+        if !already_configured {
+            let _ = installation.remove_remote(
+                &lf_remote.name().unwrap(),
+                libflatpak::gio::Cancellable::current().as_ref(),
+            );
+        }
+        info
+    }
 }
 
 impl TryFrom<Remote> for libflatpak::Remote {
@@ -113,12 +485,85 @@ impl TryFrom<Remote> for libflatpak::Remote {
         if remote.name().unwrap().to_string() == "flathub".to_string() {
             remote.set_default_branch("stable");
         }
+        if let Some(gpg_key) = &value.gpg_key {
+            let key_bytes = gpg_key.load()?;
+            remote.set_gpg_key(&libflatpak::glib::Bytes::from_owned(key_bytes));
+            remote.set_gpg_verify(true);
+        }
         Ok(remote)
     }
 
     type Error = FlatpakExtError;
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A plain, serializable summary of what a remote actually offers, for inspecting a
+/// `.flatpakrepo` (or passing its contents across a process/IPC boundary) before committing to
+/// an install.
+pub struct RemoteInfo {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub default_branch: Option<String>,
+    pub collection_id: Option<String>,
+    pub gpg_verify: bool,
+    /// Formatted refs (e.g. `app/org.mozilla.firefox/x86_64/stable`) available on this remote.
+    pub refs: Vec<String>,
+}
+
+/// Fetches and summarizes a configured [`libflatpak::Remote`]'s metadata.
+pub trait RemoteInfoExt {
+    fn info(&self, installation: &libflatpak::Installation) -> Result<RemoteInfo, FlatpakExtError>;
+}
+
+impl RemoteInfoExt for libflatpak::Remote {
+    fn info(&self, installation: &libflatpak::Installation) -> Result<RemoteInfo, FlatpakExtError> {
+        use libflatpak::prelude::RefExt;
+
+        let name = self.name().unwrap().to_string();
+        let refs = installation
+            .list_remote_refs_sync(&name, libflatpak::gio::Cancellable::current().as_ref())?
+            .iter()
+            .filter_map(|r| r.format_ref().map(|f| f.to_string()))
+            .collect();
+
+        Ok(RemoteInfo {
+            title: self.title().map(|t| t.to_string()),
+            url: self.url().map(|u| u.to_string()),
+            default_branch: self.default_branch().map(|b| b.to_string()),
+            collection_id: self.collection_id().map(|c| c.to_string()),
+            gpg_verify: self.gpg_verify(),
+            refs,
+        })
+    }
+}
+
+/// Enumerates the architectures `remote` advertises a ref under, so callers can request a
+/// specific arch explicitly and get a clear (empty) result when it isn't published.
+pub fn list_remote_ref_arches(
+    installation: &libflatpak::Installation,
+    remote: &libflatpak::Remote,
+    kind: RefKind,
+    name: &str,
+    branch: &str,
+) -> Result<Vec<String>, FlatpakExtError> {
+    use libflatpak::prelude::RefExt;
+
+    let remote_name = remote.name().unwrap().to_string();
+    Ok(installation
+        .list_remote_refs_sync(
+            &remote_name,
+            libflatpak::gio::Cancellable::current().as_ref(),
+        )?
+        .iter()
+        .filter(|r| {
+            r.kind() == kind
+                && r.name().as_deref() == Some(name)
+                && r.branch().as_deref() == Some(branch)
+        })
+        .filter_map(|r| r.arch().map(|a| a.to_string()))
+        .collect())
+}
+
 pub fn uri_to_bytes(uri: String) -> Result<libflatpak::glib::Bytes, FlatpakExtError> {
     if uri.starts_with("file://") {
         Ok(